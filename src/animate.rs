@@ -0,0 +1,162 @@
+use std::io::BufReader;
+use std::time::Duration;
+
+use image::AnimationDecoder;
+
+use crate::quantize::Octree;
+
+/// One decoded animation frame, ready to feed through the still-image
+/// rendering pipeline.
+pub struct Frame {
+    pub image: image::RgbImage,
+    pub delay: Duration,
+}
+
+/// Frame delay used for directories of frames, which (unlike GIFs) carry no
+/// authored timing of their own. Overridden by `--fps` as usual.
+const DEFAULT_FRAME_DELAY: Duration = Duration::from_millis(100);
+
+/// Whether `path` is a GIF to be decoded frame-by-frame.
+pub fn is_gif(path: &str) -> bool {
+    path.to_ascii_lowercase().ends_with(".gif")
+}
+
+/// Whether `path` should be played back as an animation (a GIF or a
+/// directory of frame images) rather than rendered as a single still image.
+pub fn is_animation(path: &str) -> bool {
+    is_gif(path) || std::path::Path::new(path).is_dir()
+}
+
+/// Decode every frame of a GIF or directory of frame images, along with its
+/// display delay (authored, for GIFs; [`DEFAULT_FRAME_DELAY`] otherwise).
+pub fn decode_frames(path: &str) -> Vec<Frame> {
+    if is_gif(path) {
+        decode_gif_frames(path)
+    } else {
+        decode_directory_frames(path)
+    }
+}
+
+fn decode_gif_frames(path: &str) -> Vec<Frame> {
+    let file = std::fs::File::open(path).unwrap();
+    let decoder = image::codecs::gif::GifDecoder::new(BufReader::new(file)).unwrap();
+
+    decoder
+        .into_frames()
+        .collect_frames()
+        .unwrap()
+        .into_iter()
+        .map(|frame| {
+            let delay = Duration::from(frame.delay());
+            let image = image::DynamicImage::ImageRgba8(frame.into_buffer()).into_rgb8();
+            Frame { image, delay }
+        })
+        .collect()
+}
+
+/// One segment of a [`natural_key`]: a run of digits compares numerically,
+/// a run of anything else compares as text.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum NaturalKeyPart {
+    Num(u64),
+    Text(String),
+}
+
+/// Split a file name into alternating digit/non-digit runs, so sorting by
+/// the result orders `frame2.png` before `frame10.png` instead of after it.
+fn natural_key(path: &std::path::Path) -> Vec<NaturalKeyPart> {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    let mut parts = Vec::new();
+    let mut chars = name.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        let mut run = String::new();
+        let is_digit = c.is_ascii_digit();
+        while let Some(&c) = chars.peek() {
+            if c.is_ascii_digit() != is_digit {
+                break;
+            }
+            run.push(c);
+            chars.next();
+        }
+        parts.push(if is_digit {
+            NaturalKeyPart::Num(run.parse().unwrap_or(u64::MAX))
+        } else {
+            NaturalKeyPart::Text(run)
+        });
+    }
+    parts
+}
+
+/// Decode every recognized image file in `path`, in natural name order
+/// (`frame2.png` before `frame10.png`, not after), as one animation frame
+/// each. Non-image files (stray readmes, thumbnail caches, `.DS_Store`) are
+/// skipped by extension, and any file that still fails to decode is skipped
+/// rather than aborting the whole playback.
+fn decode_directory_frames(path: &str) -> Vec<Frame> {
+    let mut entries: Vec<_> = std::fs::read_dir(path)
+        .unwrap()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && image::ImageFormat::from_path(path).is_ok())
+        .collect();
+    entries.sort_by_key(|path| natural_key(path));
+
+    entries
+        .into_iter()
+        .filter_map(|path| {
+            let image = image::open(&path).ok()?.into_rgb8();
+            Some(Frame {
+                image,
+                delay: DEFAULT_FRAME_DELAY,
+            })
+        })
+        .collect()
+}
+
+/// Build one shared palette across every frame, so quantized playback
+/// (`--colors`) doesn't flicker between unrelated per-frame palettes.
+pub fn build_shared_palette(frames: &[Frame], k: usize) -> Vec<image::Rgb<u8>> {
+    let mut octree = Octree::new();
+    for frame in frames {
+        for &pixel in frame.image.pixels() {
+            octree.insert(pixel);
+        }
+    }
+    octree.build_palette(k)
+}
+
+/// Loop over `frames`, clearing the terminal and invoking `render` for each
+/// one, honoring `--fps` (overriding the GIF's own per-frame delay) and
+/// `--loop` (0 meaning loop forever).
+pub fn play(
+    frames: &[Frame],
+    loop_count: u32,
+    fps: Option<f64>,
+    mut render: impl FnMut(&image::RgbImage),
+) {
+    let mut stdout = std::io::stdout();
+    let mut iteration = 0;
+    loop {
+        for frame in frames {
+            crossterm::execute!(
+                stdout,
+                crossterm::cursor::MoveTo(0, 0),
+                crossterm::terminal::Clear(crossterm::terminal::ClearType::All)
+            )
+            .unwrap();
+
+            render(&frame.image);
+
+            let delay = match fps {
+                Some(fps) if fps > 0.0 => Duration::from_secs_f64(1.0 / fps),
+                _ => frame.delay,
+            };
+            std::thread::sleep(delay);
+        }
+
+        iteration += 1;
+        if loop_count != 0 && iteration >= loop_count {
+            break;
+        }
+    }
+}
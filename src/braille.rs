@@ -0,0 +1,53 @@
+/// Whether edges are rendered at all, and if so, how.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeMode {
+    /// Render cells as plain half-blocks (the previous, only behavior).
+    None,
+    /// Overlay Canny edges as Braille dot glyphs, 2x4 dots per terminal cell.
+    Braille,
+}
+
+impl std::str::FromStr for EdgeMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "braille" => Ok(EdgeMode::Braille),
+            other => Err(format!("unknown --edges mode {other:?}, expected braille")),
+        }
+    }
+}
+
+/// Bit position (within the Braille dot-matrix encoding) of each of the 2x4
+/// sub-cell positions, indexed `[row][col]`.
+const DOT_BITS: [[u8; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// Render the 2x4 sub-cell grid of `edges` covering one terminal cell as a
+/// single Braille glyph (Unicode U+2800 plus the bitmask of present dots).
+///
+/// `cell_x`/`cell_y` are the terminal cell coordinates; `columns`/`rows` the
+/// terminal grid size; `edges` the full-resolution edge overlay being sampled.
+pub fn cell_braille_glyph(
+    edges: &image::RgbaImage,
+    columns: u32,
+    rows: u32,
+    cell_x: u32,
+    cell_y: u32,
+) -> char {
+    let (width, height) = edges.dimensions();
+    let mut bits: u32 = 0;
+
+    for (row, dot_row) in DOT_BITS.iter().enumerate() {
+        for (col, &bit) in dot_row.iter().enumerate() {
+            let sub_x = cell_x * 2 + col as u32;
+            let sub_y = cell_y * 4 + row as u32;
+            let x = (sub_x * width / (columns * 2)).min(width - 1);
+            let y = (sub_y * height / (rows * 4)).min(height - 1);
+            if edges.get_pixel(x, y).0[3] != 0 {
+                bits |= u32::from(bit);
+            }
+        }
+    }
+
+    char::from_u32(0x2800 + bits).unwrap()
+}
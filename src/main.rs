@@ -1,6 +1,72 @@
+mod animate;
+mod braille;
+mod parallel;
+mod quantize;
+mod resample;
+
+use braille::EdgeMode;
 use crossterm::style::{Print, ResetColor, SetBackgroundColor, SetForegroundColor};
 use image::Pixel;
 use palette::{FromColor, Lab, Srgb};
+use quantize::ColorMode;
+use resample::FilterMode;
+
+/// Number of palette entries derived for shared-palette animation playback.
+const ANIMATION_PALETTE_SIZE: usize = 256;
+
+struct Args {
+    image_path: String,
+    color_mode: ColorMode,
+    filter_mode: FilterMode,
+    edge_mode: EdgeMode,
+    loop_count: u32,
+    fps: Option<f64>,
+}
+
+fn parse_args() -> Args {
+    let mut image_path = None;
+    let mut color_mode = ColorMode::TrueColor;
+    let mut filter_mode = FilterMode::Point;
+    let mut edge_mode = EdgeMode::None;
+    let mut loop_count = 1;
+    let mut fps = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--colors" => {
+                let value = args.next().expect("--colors expects a value");
+                color_mode = value.parse().unwrap();
+            }
+            "--filter" => {
+                let value = args.next().expect("--filter expects a value");
+                filter_mode = value.parse().unwrap();
+            }
+            "--edges" => {
+                let value = args.next().expect("--edges expects a value");
+                edge_mode = value.parse().unwrap();
+            }
+            "--loop" => {
+                let value = args.next().expect("--loop expects a value");
+                loop_count = value.parse().expect("--loop expects an integer (0 = forever)");
+            }
+            "--fps" => {
+                let value = args.next().expect("--fps expects a value");
+                fps = Some(value.parse().expect("--fps expects a number"));
+            }
+            other => image_path = Some(other.to_owned()),
+        }
+    }
+    Args {
+        image_path: image_path.expect(
+            "usage: pixpix [--colors {truecolor,256,16}] [--filter {point,box,gaussian,mitchell}] [--edges braille] [--loop N] [--fps N] <image>",
+        ),
+        color_mode,
+        filter_mode,
+        edge_mode,
+        loop_count,
+        fps,
+    }
+}
 
 fn compute_histogram(image: &image::GrayImage) -> [u32; 256] {
     let mut hist = [0u32; 256];
@@ -72,31 +138,28 @@ fn get_image_edge_overlay(img: &image::DynamicImage) -> image::RgbaImage {
     overlay
 }
 
-fn main() {
-    let image_path = std::env::args().skip(1).next().unwrap();
-
-    let (columns, rows) = crossterm::terminal::size().unwrap();
-    let columns = u32::from(columns);
-    let rows = u32::from(rows) - 2;
-
-    let image = image::open(image_path).unwrap();
-    let image = image.into_rgb8();
+/// Render one frame to stdout: SLIC mean-color smoothing, cell resampling,
+/// optional dithered shared-palette quantization, optional edge overlay,
+/// then terminal color quantization and printing.
+fn render_frame(
+    image: &image::RgbImage,
+    args: &Args,
+    term_columns: u32,
+    term_rows: u32,
+    quantizer: &quantize::Quantizer,
+    shared_palette: Option<&quantize::LabPalette>,
+) {
+    let dynamic_image = image::DynamicImage::ImageRgb8(image.clone());
     let (width, height) = image.dimensions();
 
-    let (columns, rows) = if rows * 2 * width < columns * height {
-        (rows * 2 * width / height, rows)
+    let (columns, rows) = if term_rows * 2 * width < term_columns * height {
+        (term_rows * 2 * width / height, term_rows)
     } else {
-        (columns, columns * height / width / 2)
+        (term_columns, term_columns * height / width / 2)
     };
 
-    let unit_width = width / columns;
-    let unit_height = height / (rows * 2);
-
     let lab_buffer: Vec<Lab<_, f64>> =
-        palette::cast::from_component_slice::<Srgb<u8>>(image.as_raw())
-            .iter()
-            .map(|&c| Lab::from_color(c.into_format()))
-            .collect();
+        parallel::to_lab_buffer(palette::cast::from_component_slice::<Srgb<u8>>(image.as_raw()));
     let k = columns * rows * 2;
     let m = 10;
     let labels = simple_clustering::slic(k, m, width, height, None, &lab_buffer).unwrap();
@@ -105,24 +168,71 @@ fn main() {
         .unwrap();
     let mean_color_image_with_edges =
         image::RgbImage::from_raw(width, height, mean_color_image).unwrap();
+    let mean_lab_buffer: Vec<Lab<_, f64>> = parallel::to_lab_buffer(
+        palette::cast::from_component_slice::<Srgb<u8>>(mean_color_image_with_edges.as_raw()),
+    );
 
-    let result: image::RgbImage = image::ImageBuffer::from_fn(columns, 2 * rows, |x, y| {
-        let x = unit_width / 2 + x * width / columns;
-        let y = unit_height / 2 + y * height / (rows * 2);
-        mean_color_image_with_edges.get_pixel(x, y).to_owned()
+    let half_block_grid = resample::Grid {
+        width,
+        height,
+        columns,
+        rows: rows * 2,
+    };
+    let mut result: image::RgbImage = parallel::build_cell_image(columns, 2 * rows, |x, y| {
+        let lab = resample::resample_cell(&mean_lab_buffer, half_block_grid, x, y, args.filter_mode);
+        let srgb: Srgb<u8> = Srgb::from_color(lab).into_format();
+        image::Rgb([srgb.red, srgb.green, srgb.blue])
     });
+    if let Some(palette) = shared_palette {
+        quantize::dither_to_palette(&mut result, palette);
+    }
 
     let mut stdout = std::io::stdout();
 
+    if args.edge_mode == EdgeMode::Braille {
+        let edges = get_image_edge_overlay(&dynamic_image);
+        let cell_grid = resample::Grid {
+            width,
+            height,
+            columns,
+            rows,
+        };
+        let mut cell_background: image::RgbImage = parallel::build_cell_image(columns, rows, |x, y| {
+            let lab = resample::resample_cell(&mean_lab_buffer, cell_grid, x, y, args.filter_mode);
+            let srgb: Srgb<u8> = Srgb::from_color(lab).into_format();
+            image::Rgb([srgb.red, srgb.green, srgb.blue])
+        });
+        if let Some(palette) = shared_palette {
+            quantize::dither_to_palette(&mut cell_background, palette);
+        }
+
+        for row in 0..rows {
+            for col in 0..columns {
+                let background = cell_background.get_pixel(col, row).to_rgb();
+                let background = quantizer.quantize(background);
+                let glyph = braille::cell_braille_glyph(&edges, columns, rows, col, row);
+
+                crossterm::execute!(
+                    stdout,
+                    SetForegroundColor(crossterm::style::Color::White),
+                    SetBackgroundColor(background),
+                    Print(glyph.to_string())
+                )
+                .unwrap();
+            }
+            crossterm::execute!(stdout, ResetColor).unwrap();
+            println!("");
+        }
+        return;
+    }
+
     for row in (0..2 * rows).step_by(2) {
         for col in 0..columns {
             let background = result.get_pixel(col, row).to_rgb();
             let foreground = result.get_pixel(col, row + 1).to_rgb();
 
-            let [r, g, b] = background.0;
-            let background = crossterm::style::Color::Rgb { r, g, b };
-            let [r, g, b] = foreground.0;
-            let foreground = crossterm::style::Color::Rgb { r, g, b };
+            let background = quantizer.quantize(background);
+            let foreground = quantizer.quantize(foreground);
 
             crossterm::execute!(
                 stdout,
@@ -136,3 +246,37 @@ fn main() {
         println!("");
     }
 }
+
+fn main() {
+    let args = parse_args();
+
+    let (term_columns, term_rows) = crossterm::terminal::size().unwrap();
+    let term_columns = u32::from(term_columns);
+    let term_rows = u32::from(term_rows) - 2;
+
+    let quantizer = quantize::Quantizer::new(args.color_mode);
+
+    if animate::is_animation(&args.image_path) {
+        let frames = animate::decode_frames(&args.image_path);
+        // Only snap to one shared palette when we're already quantizing colors:
+        // one palette across all frames keeps quantized playback flicker-free,
+        // while truecolor playback is left without a needless adaptive-palette
+        // downgrade.
+        let shared_palette = (args.color_mode != ColorMode::TrueColor).then(|| {
+            quantize::LabPalette::new(animate::build_shared_palette(&frames, ANIMATION_PALETTE_SIZE))
+        });
+        animate::play(&frames, args.loop_count, args.fps, |frame| {
+            render_frame(
+                frame,
+                &args,
+                term_columns,
+                term_rows,
+                &quantizer,
+                shared_palette.as_ref(),
+            );
+        });
+    } else {
+        let image = image::open(&args.image_path).unwrap().into_rgb8();
+        render_frame(&image, &args, term_columns, term_rows, &quantizer, None);
+    }
+}
@@ -0,0 +1,57 @@
+//! Pixel-map helpers for the two embarrassingly-parallel hot loops in
+//! rendering: the per-pixel sRGB->Lab conversion and the per-cell resampling
+//! that builds the output image. Both run serially unless the `parallel`
+//! cargo feature is enabled, in which case they fan out across cores with
+//! rayon.
+
+use image::Rgb;
+use palette::{white_point::D65, FromColor, Lab, Srgb};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Convert an sRGB pixel buffer to Lab, one color per pixel.
+pub fn to_lab_buffer(pixels: &[Srgb<u8>]) -> Vec<Lab<D65, f64>> {
+    #[cfg(feature = "parallel")]
+    {
+        pixels
+            .par_iter()
+            .map(|&c| Lab::from_color(c.into_format()))
+            .collect()
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        pixels
+            .iter()
+            .map(|&c| Lab::from_color(c.into_format()))
+            .collect()
+    }
+}
+
+/// Build a `columns`x`rows` image by independently computing each cell's
+/// color with `f`, parallelizing row-by-row when the `parallel` feature is on.
+pub fn build_cell_image(
+    columns: u32,
+    rows: u32,
+    f: impl Fn(u32, u32) -> Rgb<u8> + Sync,
+) -> image::RgbImage {
+    #[cfg(feature = "parallel")]
+    {
+        let mut buffer = vec![0u8; (columns * rows * 3) as usize];
+        buffer
+            .par_chunks_mut((columns * 3) as usize)
+            .enumerate()
+            .for_each(|(y, row)| {
+                for x in 0..columns {
+                    let color = f(x, y as u32);
+                    let offset = (x * 3) as usize;
+                    row[offset..offset + 3].copy_from_slice(&color.0);
+                }
+            });
+        image::RgbImage::from_raw(columns, rows, buffer).unwrap()
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        image::ImageBuffer::from_fn(columns, rows, &f)
+    }
+}
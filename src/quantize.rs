@@ -0,0 +1,403 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use image::Rgb;
+use palette::{FromColor, Lab, Srgb};
+
+/// Target color representation for a rendered cell, selected by `--colors`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    /// Emit 24-bit `Color::Rgb` escapes directly (the previous, unconditional behavior).
+    TrueColor,
+    /// Quantize to the fixed xterm 256-color cube and emit `Color::AnsiValue`.
+    Palette256,
+    /// Quantize to the 16 named ANSI colors.
+    Ansi16,
+}
+
+impl std::str::FromStr for ColorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "truecolor" => Ok(ColorMode::TrueColor),
+            "256" => Ok(ColorMode::Palette256),
+            "16" => Ok(ColorMode::Ansi16),
+            other => Err(format!(
+                "unknown --colors mode {other:?}, expected one of truecolor, 256, 16"
+            )),
+        }
+    }
+}
+
+const MAX_DEPTH: usize = 8;
+
+struct Node {
+    children: [Option<usize>; 8],
+    parent: Option<usize>,
+    depth: usize,
+    r: u64,
+    g: u64,
+    b: u64,
+    pixel_count: u64,
+    is_leaf: bool,
+}
+
+impl Node {
+    fn new(depth: usize, parent: Option<usize>) -> Self {
+        Node {
+            children: [None; 8],
+            parent,
+            depth,
+            r: 0,
+            g: 0,
+            b: 0,
+            pixel_count: 0,
+            is_leaf: depth == MAX_DEPTH,
+        }
+    }
+
+    fn child_index(color: Rgb<u8>, depth: usize) -> usize {
+        let shift = 7 - depth;
+        let r_bit = (color.0[0] >> shift) & 1;
+        let g_bit = (color.0[1] >> shift) & 1;
+        let b_bit = (color.0[2] >> shift) & 1;
+        usize::from((r_bit << 2) | (g_bit << 1) | b_bit)
+    }
+
+    fn average(&self) -> Rgb<u8> {
+        if self.pixel_count == 0 {
+            return Rgb([0, 0, 0]);
+        }
+        Rgb([
+            (self.r / self.pixel_count) as u8,
+            (self.g / self.pixel_count) as u8,
+            (self.b / self.pixel_count) as u8,
+        ])
+    }
+}
+
+/// An octree color quantizer: insert every source pixel, then reduce the tree
+/// down to a palette of at most `k` entries whose colors best represent the
+/// inserted distribution.
+pub struct Octree {
+    nodes: Vec<Node>,
+    leaf_count: usize,
+}
+
+impl Octree {
+    pub fn new() -> Self {
+        Octree {
+            nodes: vec![Node::new(0, None)],
+            leaf_count: 0,
+        }
+    }
+
+    pub fn insert(&mut self, color: Rgb<u8>) {
+        let mut node_idx = 0;
+        loop {
+            let node = &mut self.nodes[node_idx];
+            node.r += u64::from(color.0[0]);
+            node.g += u64::from(color.0[1]);
+            node.b += u64::from(color.0[2]);
+            node.pixel_count += 1;
+
+            let depth = node.depth;
+            if depth == MAX_DEPTH {
+                if !node.is_leaf {
+                    node.is_leaf = true;
+                    self.leaf_count += 1;
+                }
+                return;
+            }
+
+            let child_slot = Node::child_index(color, depth);
+            if let Some(child_idx) = node.children[child_slot] {
+                node_idx = child_idx;
+                continue;
+            }
+
+            let child_idx = self.nodes.len();
+            self.nodes.push(Node::new(depth + 1, Some(node_idx)));
+            self.nodes[node_idx].children[child_slot] = Some(child_idx);
+            if depth + 1 == MAX_DEPTH {
+                self.leaf_count += 1;
+                self.nodes[child_idx].is_leaf = true;
+            }
+            node_idx = child_idx;
+        }
+    }
+
+    /// Reduce the tree to at most `k` leaves and return the averaged color of
+    /// each surviving leaf.
+    pub fn build_palette(mut self, k: usize) -> Vec<Rgb<u8>> {
+        let mut reducible: BinaryHeap<Reverse<(usize, u64, usize)>> = BinaryHeap::new();
+        for (idx, node) in self.nodes.iter().enumerate() {
+            if !node.is_leaf && self.is_reducible(idx) {
+                reducible.push(Reverse((MAX_DEPTH - node.depth, node.pixel_count, idx)));
+            }
+        }
+
+        while self.leaf_count > k.max(1) {
+            let Some(Reverse((_, _, node_idx))) = reducible.pop() else {
+                break;
+            };
+            if self.nodes[node_idx].is_leaf || !self.is_reducible(node_idx) {
+                continue;
+            }
+            self.fold(node_idx);
+
+            if let Some(parent_idx) = self.nodes[node_idx].parent {
+                if self.is_reducible(parent_idx) {
+                    let parent = &self.nodes[parent_idx];
+                    reducible.push(Reverse((
+                        MAX_DEPTH - parent.depth,
+                        parent.pixel_count,
+                        parent_idx,
+                    )));
+                }
+            }
+        }
+
+        let mut palette = Vec::new();
+        self.collect_leaves(0, &mut palette);
+        palette
+    }
+
+    /// Walk the live tree from `idx`, collecting the averaged color of every
+    /// leaf reachable through `children`. A flat scan of `self.nodes` would
+    /// also pick up nodes that were leaves before an ancestor folded over
+    /// them; those are orphaned (no longer reachable from the root) once
+    /// `fold` clears the ancestor's `children`, so only a tree walk reports
+    /// the current leaf set.
+    fn collect_leaves(&self, idx: usize, out: &mut Vec<Rgb<u8>>) {
+        let node = &self.nodes[idx];
+        if node.is_leaf {
+            if node.pixel_count > 0 {
+                out.push(node.average());
+            }
+            return;
+        }
+        for child in node.children.iter().flatten() {
+            self.collect_leaves(*child, out);
+        }
+    }
+
+    /// A node is reducible once it is internal and every child it has is a leaf.
+    fn is_reducible(&self, idx: usize) -> bool {
+        let node = &self.nodes[idx];
+        if node.is_leaf {
+            return false;
+        }
+        let mut has_child = false;
+        for child in node.children.iter().flatten() {
+            has_child = true;
+            if !self.nodes[*child].is_leaf {
+                return false;
+            }
+        }
+        has_child
+    }
+
+    /// Fold a reducible node's children back into itself, turning it into a leaf.
+    fn fold(&mut self, idx: usize) {
+        let children = self.nodes[idx].children;
+        self.leaf_count -= children.iter().flatten().count();
+        self.nodes[idx].children = [None; 8];
+        self.nodes[idx].is_leaf = true;
+        self.leaf_count += 1;
+    }
+}
+
+type LabF64 = Lab<palette::white_point::D65, f64>;
+
+fn to_lab(color: Rgb<u8>) -> LabF64 {
+    let srgb: Srgb<u8> = Srgb::new(color.0[0], color.0[1], color.0[2]);
+    Lab::from_color(srgb.into_format::<f64>())
+}
+
+fn lab_distance(a: LabF64, b: LabF64) -> f64 {
+    (a.l - b.l).powi(2) + (a.a - b.a).powi(2) + (a.b - b.b).powi(2)
+}
+
+/// A palette of RGB colors with their Lab values precomputed once, so repeated
+/// nearest-color lookups (one per rendered cell) don't redo the sRGB->Lab
+/// conversion of the whole candidate set every time.
+pub struct LabPalette {
+    colors: Vec<Rgb<u8>>,
+    labs: Vec<LabF64>,
+}
+
+impl LabPalette {
+    pub fn new(colors: Vec<Rgb<u8>>) -> Self {
+        let labs = colors.iter().map(|&c| to_lab(c)).collect();
+        LabPalette { colors, labs }
+    }
+
+    /// Index of the palette entry perceptually closest to `color` in Lab space.
+    pub fn nearest_index(&self, color: Rgb<u8>) -> usize {
+        let target = to_lab(color);
+        self.labs
+            .iter()
+            .enumerate()
+            .map(|(i, &lab)| (i, lab_distance(lab, target)))
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+            .map(|(i, _)| i)
+            .unwrap_or(0)
+    }
+
+    pub fn nearest_color(&self, color: Rgb<u8>) -> Rgb<u8> {
+        self.colors[self.nearest_index(color)]
+    }
+}
+
+/// The 256-color xterm palette: 16 system colors, a 6x6x6 color cube, and a
+/// 24-step grayscale ramp.
+pub fn xterm_256_palette() -> Vec<Rgb<u8>> {
+    const SYSTEM: [[u8; 3]; 16] = [
+        [0, 0, 0],
+        [128, 0, 0],
+        [0, 128, 0],
+        [128, 128, 0],
+        [0, 0, 128],
+        [128, 0, 128],
+        [0, 128, 128],
+        [192, 192, 192],
+        [128, 128, 128],
+        [255, 0, 0],
+        [0, 255, 0],
+        [255, 255, 0],
+        [0, 0, 255],
+        [255, 0, 255],
+        [0, 255, 255],
+        [255, 255, 255],
+    ];
+    const CUBE_STEPS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+    let mut palette = Vec::with_capacity(256);
+    palette.extend(SYSTEM.iter().map(|&c| Rgb(c)));
+    for r in CUBE_STEPS {
+        for g in CUBE_STEPS {
+            for b in CUBE_STEPS {
+                palette.push(Rgb([r, g, b]));
+            }
+        }
+    }
+    for step in 0..24u8 {
+        let v = 8 + step * 10;
+        palette.push(Rgb([v, v, v]));
+    }
+    palette
+}
+
+/// The 16 named ANSI colors, paired with the `crossterm` color they should
+/// be emitted as.
+pub fn ansi_16_palette() -> Vec<(crossterm::style::Color, Rgb<u8>)> {
+    use crossterm::style::Color;
+    vec![
+        (Color::Black, Rgb([0, 0, 0])),
+        (Color::DarkRed, Rgb([128, 0, 0])),
+        (Color::DarkGreen, Rgb([0, 128, 0])),
+        (Color::DarkYellow, Rgb([128, 128, 0])),
+        (Color::DarkBlue, Rgb([0, 0, 128])),
+        (Color::DarkMagenta, Rgb([128, 0, 128])),
+        (Color::DarkCyan, Rgb([0, 128, 128])),
+        (Color::Grey, Rgb([192, 192, 192])),
+        (Color::DarkGrey, Rgb([128, 128, 128])),
+        (Color::Red, Rgb([255, 0, 0])),
+        (Color::Green, Rgb([0, 255, 0])),
+        (Color::Yellow, Rgb([255, 255, 0])),
+        (Color::Blue, Rgb([0, 0, 255])),
+        (Color::Magenta, Rgb([255, 0, 255])),
+        (Color::Cyan, Rgb([0, 255, 255])),
+        (Color::White, Rgb([255, 255, 255])),
+    ]
+}
+
+/// Quantize every pixel of `image` to the closest entry of a fixed palette
+/// (e.g. the shared per-animation palette built from
+/// [`Octree::build_palette`]), Floyd-Steinberg error-diffusion dithering the
+/// quantization error into neighboring pixels so flat regions of the shared
+/// palette don't band as harshly as plain nearest-color snapping would.
+pub fn dither_to_palette(image: &mut image::RgbImage, palette: &LabPalette) {
+    let (width, height) = image.dimensions();
+    let mut error = vec![[0.0f32; 3]; (width * height) as usize];
+
+    let diffuse = |error: &mut [[f32; 3]], x: i64, y: i64, dx: i64, dy: i64, amount: f32, err: [f32; 3]| {
+        let (nx, ny) = (x + dx, y + dy);
+        if nx < 0 || nx >= i64::from(width) || ny < 0 || ny >= i64::from(height) {
+            return;
+        }
+        let idx = (ny as u32 * width + nx as u32) as usize;
+        for channel in 0..3 {
+            error[idx][channel] += err[channel] * amount;
+        }
+    };
+
+    for y in 0..height {
+        for x in 0..width {
+            let idx = (y * width + x) as usize;
+            let source = image.get_pixel(x, y).0;
+            let adjusted = [
+                f32::from(source[0]) + error[idx][0],
+                f32::from(source[1]) + error[idx][1],
+                f32::from(source[2]) + error[idx][2],
+            ];
+            let clamped = Rgb(adjusted.map(|c| c.clamp(0.0, 255.0) as u8));
+            let quantized = palette.nearest_color(clamped);
+            image.put_pixel(x, y, quantized);
+
+            let err = [
+                adjusted[0] - f32::from(quantized.0[0]),
+                adjusted[1] - f32::from(quantized.0[1]),
+                adjusted[2] - f32::from(quantized.0[2]),
+            ];
+            let (x, y) = (i64::from(x), i64::from(y));
+            diffuse(&mut error, x, y, 1, 0, 7.0 / 16.0, err);
+            diffuse(&mut error, x, y, -1, 1, 3.0 / 16.0, err);
+            diffuse(&mut error, x, y, 0, 1, 5.0 / 16.0, err);
+            diffuse(&mut error, x, y, 1, 1, 1.0 / 16.0, err);
+        }
+    }
+}
+
+/// Quantizes cell colors to the representation selected by `--colors`. Built
+/// once up front so the candidate palette (and its Lab conversions) isn't
+/// recomputed for every cell or frame.
+pub enum Quantizer {
+    TrueColor,
+    Palette256(LabPalette),
+    Ansi16 {
+        palette: LabPalette,
+        colors: Vec<crossterm::style::Color>,
+    },
+}
+
+impl Quantizer {
+    pub fn new(mode: ColorMode) -> Self {
+        match mode {
+            ColorMode::TrueColor => Quantizer::TrueColor,
+            ColorMode::Palette256 => Quantizer::Palette256(LabPalette::new(xterm_256_palette())),
+            ColorMode::Ansi16 => {
+                let named = ansi_16_palette();
+                let colors = named.iter().map(|&(color, _)| color).collect();
+                let palette = LabPalette::new(named.into_iter().map(|(_, rgb)| rgb).collect());
+                Quantizer::Ansi16 { palette, colors }
+            }
+        }
+    }
+
+    /// Quantize `color`, producing the `crossterm` color to emit.
+    pub fn quantize(&self, color: Rgb<u8>) -> crossterm::style::Color {
+        match self {
+            Quantizer::TrueColor => {
+                let [r, g, b] = color.0;
+                crossterm::style::Color::Rgb { r, g, b }
+            }
+            Quantizer::Palette256(palette) => {
+                crossterm::style::Color::AnsiValue(palette.nearest_index(color) as u8)
+            }
+            Quantizer::Ansi16 { palette, colors } => colors[palette.nearest_index(color)],
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use palette::Lab;
+
+/// Reconstruction filter used when resampling the source image down to one
+/// color per terminal cell, selected by `--filter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterMode {
+    /// Sample a single source pixel nearest the cell center (the previous behavior).
+    Point,
+    /// Average every source pixel covering the cell.
+    Box,
+    /// Gaussian-weighted average, truncated at a 2px radius.
+    Gaussian,
+    /// Mitchell-Netravali cubic filter with B = C = 1/3.
+    Mitchell,
+}
+
+impl std::str::FromStr for FilterMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "point" => Ok(FilterMode::Point),
+            "box" => Ok(FilterMode::Box),
+            "gaussian" => Ok(FilterMode::Gaussian),
+            "mitchell" => Ok(FilterMode::Mitchell),
+            other => Err(format!(
+                "unknown --filter mode {other:?}, expected one of point, box, gaussian, mitchell"
+            )),
+        }
+    }
+}
+
+type LabF64 = Lab<palette::white_point::D65, f64>;
+
+/// The source image size and the output cell grid it's being resampled down to.
+#[derive(Debug, Clone, Copy)]
+pub struct Grid {
+    pub width: u32,
+    pub height: u32,
+    pub columns: u32,
+    pub rows: u32,
+}
+
+fn mitchell_netravali(d: f64) -> f64 {
+    const B: f64 = 1.0 / 3.0;
+    const C: f64 = 1.0 / 3.0;
+    let d = d.abs();
+    if d < 1.0 {
+        ((12.0 - 9.0 * B - 6.0 * C) * d.powi(3)
+            + (-18.0 + 12.0 * B + 6.0 * C) * d.powi(2)
+            + (6.0 - 2.0 * B))
+            / 6.0
+    } else if d < 2.0 {
+        ((-B - 6.0 * C) * d.powi(3)
+            + (6.0 * B + 30.0 * C) * d.powi(2)
+            + (-12.0 * B - 48.0 * C) * d
+            + (8.0 * B + 24.0 * C))
+            / 6.0
+    } else {
+        0.0
+    }
+}
+
+fn gaussian(d: f64) -> f64 {
+    if d >= 2.0 {
+        0.0
+    } else {
+        (-2.0 * d * d).exp()
+    }
+}
+
+/// Resample `lab_buffer` (row-major, covering `grid.width`x`grid.height`)
+/// down to one Lab color per output cell, covering the window of source
+/// pixels that maps onto it.
+pub fn resample_cell(
+    lab_buffer: &[LabF64],
+    grid: Grid,
+    cell_x: u32,
+    cell_y: u32,
+    filter: FilterMode,
+) -> LabF64 {
+    let Grid {
+        width,
+        height,
+        columns,
+        rows,
+    } = grid;
+
+    if filter == FilterMode::Point {
+        let unit_width = width / columns;
+        let unit_height = height / rows;
+        let x = unit_width / 2 + cell_x * width / columns;
+        let y = unit_height / 2 + cell_y * height / rows;
+        return lab_buffer[(y * width + x) as usize];
+    }
+
+    let scale_x = width as f64 / columns as f64;
+    let scale_y = height as f64 / rows as f64;
+    let center_x = (cell_x as f64 + 0.5) * scale_x;
+    let center_y = (cell_y as f64 + 0.5) * scale_y;
+
+    let radius = match filter {
+        FilterMode::Box => 0.5_f64.max(scale_x.max(scale_y) / 2.0),
+        FilterMode::Gaussian | FilterMode::Mitchell => (scale_x.max(scale_y) / 2.0).max(1.0) * 2.0,
+        FilterMode::Point => unreachable!(),
+    };
+
+    let min_x = (center_x - radius).floor().max(0.0) as u32;
+    let max_x = (center_x + radius).ceil().min(width as f64 - 1.0) as u32;
+    let min_y = (center_y - radius).floor().max(0.0) as u32;
+    let max_y = (center_y + radius).ceil().min(height as f64 - 1.0) as u32;
+
+    let mut weight_sum = 0.0;
+    let mut l = 0.0;
+    let mut a = 0.0;
+    let mut b = 0.0;
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            let dx = (x as f64 + 0.5 - center_x) / (scale_x.max(1.0) / 2.0);
+            let dy = (y as f64 + 0.5 - center_y) / (scale_y.max(1.0) / 2.0);
+            let weight = match filter {
+                FilterMode::Box => {
+                    if dx.abs() <= 1.0 && dy.abs() <= 1.0 {
+                        1.0
+                    } else {
+                        0.0
+                    }
+                }
+                FilterMode::Gaussian => gaussian((dx * dx + dy * dy).sqrt()),
+                FilterMode::Mitchell => mitchell_netravali(dx) * mitchell_netravali(dy),
+                FilterMode::Point => unreachable!(),
+            };
+            if weight == 0.0 {
+                continue;
+            }
+            let color = lab_buffer[(y * width + x) as usize];
+            l += color.l * weight;
+            a += color.a * weight;
+            b += color.b * weight;
+            weight_sum += weight;
+        }
+    }
+
+    if weight_sum == 0.0 {
+        return lab_buffer[(center_y as u32 * width + center_x as u32) as usize];
+    }
+
+    Lab::new(l / weight_sum, a / weight_sum, b / weight_sum)
+}